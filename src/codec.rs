@@ -0,0 +1,185 @@
+//! Dedicated streaming binary wire format for [`SgData`].
+//!
+//! Unlike going through a generic `serde` backend (which buffers the whole
+//! payload into an intermediate `Vec<u8>`), [`write_to`] streams each
+//! segment straight to an `io::Write` and [`read_from`] reads one back from
+//! an `io::Read` (including a plain `&[u8]`), preserving `Zle` segments
+//! exactly instead of collapsing them into literal bytes.
+//!
+//! Frame layout, all integers big-endian:
+//!
+//! ```text
+//! version: u8
+//! segment_count: u32
+//! segment*:
+//!     kind: u8       // 0 = literal, 1 = zero-run
+//!     len: u64
+//!     bytes: [u8; len]  // only present when kind == 0
+//! ```
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use super::{Element, SgData};
+
+const VERSION: u8 = 1;
+const KIND_LITERAL: u8 = 0;
+const KIND_ZERO_RUN: u8 = 1;
+
+/// Upper bound on a frame's declared segment count, checked before
+/// preallocating the decoded `Vec<Element>` so a malformed/hostile count
+/// read off the wire can't force a huge upfront allocation.
+const MAX_SEGMENT_COUNT: u32 = 16 * 1024 * 1024;
+
+/// Largest chunk read at once for a literal segment's payload, so a
+/// malformed/hostile `len` can't force a single huge upfront allocation
+/// before any content byte has actually been read; the buffer still grows
+/// to the segment's real length, just a chunk at a time.
+const MAX_LITERAL_CHUNK: usize = 64 * 1024;
+
+/// Streams `data` to `writer` using this module's wire format, returning the
+/// number of bytes written.
+pub fn write_to<W>(data: &SgData, writer: &mut W) -> io::Result<usize>
+where
+    W: Write,
+{
+    writer.write_all(&[VERSION])?;
+    let mut written = 1;
+
+    match *data {
+        SgData::SgList(ref sglist) => {
+            written += write_segment_count(writer, sglist.count as usize)?;
+            for idx in 0..sglist.count as isize {
+                let buf = unsafe {
+                    let iov = sglist.iovec.offset(idx);
+                    let base = (*iov).iov_base as *const u8;
+                    let len = (*iov).iov_len as usize;
+                    ::std::slice::from_raw_parts(base, len)
+                };
+                written += write_literal(writer, buf)?;
+            }
+        }
+        SgData::SgVec(ref sgvec) => {
+            written += write_segment_count(writer, sgvec.len())?;
+            for segment in sgvec {
+                written += write_literal(writer, segment)?;
+            }
+        }
+        SgData::Direct(ref buf) => {
+            written += write_segment_count(writer, 1)?;
+            written += write_literal(writer, buf)?;
+        }
+        SgData::Element(ref elements) => {
+            written += write_segment_count(writer, elements.len())?;
+            for element in elements {
+                written += match *element {
+                    Element::Zle(size) => write_zero_run(writer, size)?,
+                    Element::Iovec(ref iov) => {
+                        let buf = unsafe {
+                            let base = iov.iov_base as *const u8;
+                            let len = iov.iov_len as usize;
+                            ::std::slice::from_raw_parts(base, len)
+                        };
+                        write_literal(writer, buf)?
+                    }
+                    Element::Owned(ref buf) => write_literal(writer, buf)?,
+                };
+            }
+        }
+    }
+
+    Ok(written)
+}
+
+/// Reads an `SgData` back from `reader`, always yielding the `Element`
+/// variant so `Zle` segments survive the round trip.
+pub fn read_from<R>(reader: &mut R) -> io::Result<SgData>
+where
+    R: Read,
+{
+    let mut version = [0_u8; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported sgdata wire version {}", version[0]),
+        ));
+    }
+
+    let mut count_buf = [0_u8; 4];
+    reader.read_exact(&mut count_buf)?;
+    let count = u32::from_be_bytes(count_buf);
+    if count > MAX_SEGMENT_COUNT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("sgdata wire segment count {} exceeds the {} limit", count, MAX_SEGMENT_COUNT),
+        ));
+    }
+    let count = count as usize;
+
+    let mut elements = Vec::with_capacity(count);
+    for _ in 0..count {
+        elements.push(read_segment(reader)?);
+    }
+
+    Ok(SgData::Element(elements))
+}
+
+fn write_segment_count<W>(writer: &mut W, count: usize) -> io::Result<usize>
+where
+    W: Write,
+{
+    writer.write_all(&(count as u32).to_be_bytes())?;
+    Ok(4)
+}
+
+fn write_literal<W>(writer: &mut W, bytes: &[u8]) -> io::Result<usize>
+where
+    W: Write,
+{
+    writer.write_all(&[KIND_LITERAL])?;
+    writer.write_all(&(bytes.len() as u64).to_be_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(1 + 8 + bytes.len())
+}
+
+fn write_zero_run<W>(writer: &mut W, len: usize) -> io::Result<usize>
+where
+    W: Write,
+{
+    writer.write_all(&[KIND_ZERO_RUN])?;
+    writer.write_all(&(len as u64).to_be_bytes())?;
+    Ok(1 + 8)
+}
+
+fn read_segment<R>(reader: &mut R) -> io::Result<Element>
+where
+    R: Read,
+{
+    let mut kind = [0_u8; 1];
+    reader.read_exact(&mut kind)?;
+
+    let mut len_buf = [0_u8; 8];
+    reader.read_exact(&mut len_buf)?;
+    let len = u64::from_be_bytes(len_buf) as usize;
+
+    match kind[0] {
+        KIND_LITERAL => {
+            let mut buf = Vec::new();
+            let mut remaining = len;
+            while remaining > 0 {
+                let take = cmp::min(remaining, MAX_LITERAL_CHUNK);
+                let start = buf.len();
+                buf.resize(start + take, 0);
+                reader.read_exact(&mut buf[start..])?;
+                remaining -= take;
+            }
+            Ok(Element::Owned(buf))
+        }
+        KIND_ZERO_RUN => Ok(Element::Zle(len)),
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown sgdata wire segment kind {}", other),
+        )),
+    }
+}
@@ -10,24 +10,48 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod codec;
+
+use std::cmp;
 use std::fmt;
-use std::iter::{self, FromIterator};
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::slice;
 use std::vec;
 
 use libc::{c_int, c_void, iovec, size_t};
 use serde::de::{self, Error};
-use serde::ser::{SerializeSeq, SerializeTupleVariant};
+use serde::ser::{SerializeSeq, SerializeTuple, SerializeTupleVariant};
 use serde::{Serialize, Serializer};
 
+/// Default minimum length of a zero run worth encoding as a ZLE marker rather
+/// than folding into the surrounding literal span; see [`Sparse`].
+const DEFAULT_ZERO_RUN_THRESHOLD: usize = 16;
+
+/// Borrowed byte segment that serializes as a single opaque byte string
+/// (`Serializer::serialize_bytes`) rather than a sequence of `u8` tokens.
+struct BytesRef<'a>(&'a [u8]);
+
+impl<'a> Serialize for BytesRef<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
 /// High Level wrapper for multiple data representation methods.
 #[derive(Debug, Deserialize, PartialEq)]
 pub enum SgData {
     /// Classic Scatter Gather list as it comes from C (array of `iovec` elements)
     SgList(SgList),
     /// Vec<u8> scatter-gather list
+    #[serde(deserialize_with = "deserialize_segments")]
     SgVec(Vec<Vec<u8>>),
     /// Plain Vec<u8> buffer
+    #[serde(deserialize_with = "deserialize_buffer")]
     Direct(Vec<u8>),
     /// Special case for `iovec` array that is itself a Rust' `Vec`
     Element(Vec<Element>),
@@ -95,12 +119,13 @@ impl Serialize for SgData {
             }
             SgData::SgVec(ref sgvec) => {
                 let mut data = serializer.serialize_tuple_variant("SgData", 1, "SgVec", 1)?;
-                data.serialize_field(sgvec)?;
+                let segments = sgvec.iter().map(|seg| BytesRef(seg)).collect::<Vec<_>>();
+                data.serialize_field(&segments)?;
                 data.end()
             }
             SgData::Direct(ref buf) => {
                 let mut data = serializer.serialize_tuple_variant("SgData", 2, "Direct", 1)?;
-                data.serialize_field(buf)?;
+                data.serialize_field(&BytesRef(buf))?;
                 data.end()
             }
             SgData::Element(ref vec) => {
@@ -119,16 +144,336 @@ impl IntoIterator for SgData {
 
     fn into_iter(self) -> Self::IntoIter {
         let vec = match self {
-            SgData::SgList(_) => unimplemented!(),
+            SgData::SgList(sglist) => (0..sglist.count as isize)
+                .map(|idx| unsafe {
+                    let iov = sglist.iovec.offset(idx);
+                    let base = (*iov).iov_base as *const u8;
+                    let len = (*iov).iov_len as usize;
+                    slice::from_raw_parts(base, len).to_vec()
+                })
+                .collect(),
             SgData::SgVec(sgvec) => sgvec,
             SgData::Direct(buf) => vec![buf],
-            SgData::Element(_) => unimplemented!(),
+            SgData::Element(elements) => elements
+                .into_iter()
+                .map(|elem| match elem {
+                    Element::Zle(size) => vec![0_u8; size],
+                    Element::Iovec(iov) => unsafe {
+                        let base = iov.iov_base as *const u8;
+                        let len = iov.iov_len as usize;
+                        slice::from_raw_parts(base, len).to_vec()
+                    },
+                    Element::Owned(buf) => buf,
+                })
+                .collect(),
         };
 
         vec.into_iter()
     }
 }
 
+/// Zero bytes handed out by [`ElementIter`] for `Element::Zle` segments,
+/// chunked to this size when a run is longer than the buffer itself.
+static ZERO_CHUNK: [u8; 4096] = [0_u8; 4096];
+
+/// Borrowing, zero-copy iterator over every segment of an [`SgData`],
+/// regardless of variant; see [`SgData::iter`].
+#[derive(Debug)]
+pub enum Iter<'a> {
+    /// Iterating a `SgList`'s raw `iovec` array.
+    SgList(SgListIter<'a>),
+    /// Iterating a `SgVec`'s inner buffers.
+    SgVec(slice::Iter<'a, Vec<u8>>),
+    /// Yielding a `Direct` buffer's single slice.
+    Direct(Option<&'a [u8]>),
+    /// Iterating an `Element` list, expanding `Zle` runs on the fly.
+    Element(ElementIter<'a>),
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self {
+            Iter::SgList(ref mut iter) => iter.next(),
+            Iter::SgVec(ref mut iter) => iter.next().map(Vec::as_slice),
+            Iter::Direct(ref mut slot) => slot.take(),
+            Iter::Element(ref mut iter) => iter.next(),
+        }
+    }
+}
+
+/// Walks a `SgList`'s raw `iovec` array, yielding a slice per entry.
+#[derive(Debug)]
+pub struct SgListIter<'a> {
+    iovec: *const iovec,
+    idx: isize,
+    count: isize,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Iterator for SgListIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx >= self.count {
+            return None;
+        }
+
+        let buf = unsafe {
+            let iov = self.iovec.offset(self.idx);
+            let base = (*iov).iov_base as *const u8;
+            let len = (*iov).iov_len as usize;
+            slice::from_raw_parts(base, len)
+        };
+        self.idx += 1;
+        Some(buf)
+    }
+}
+
+/// Walks an `Element` list, yielding each `Iovec`'s slice and expanding
+/// `Zle` runs into chunks of [`ZERO_CHUNK`].
+#[derive(Debug)]
+pub struct ElementIter<'a> {
+    elements: slice::Iter<'a, Element>,
+    zero_remaining: usize,
+}
+
+impl<'a> Iterator for ElementIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Looping rather than recursing into `self.next()` for zero-length
+        // `Zle` runs matters: `Element::zero(0)` is constructible through
+        // the public API, so a long run of them must not grow the stack.
+        loop {
+            if self.zero_remaining > 0 {
+                let take = cmp::min(self.zero_remaining, ZERO_CHUNK.len());
+                self.zero_remaining -= take;
+                return Some(&ZERO_CHUNK[..take]);
+            }
+
+            match self.elements.next() {
+                Some(&Element::Zle(size)) => self.zero_remaining = size,
+                Some(&Element::Iovec(ref iov)) => {
+                    let buf = unsafe {
+                        let base = iov.iov_base as *const u8;
+                        let len = iov.iov_len as usize;
+                        slice::from_raw_parts(base, len)
+                    };
+                    return Some(buf);
+                }
+                Some(&Element::Owned(ref buf)) => return Some(buf.as_slice()),
+                None => return None,
+            }
+        }
+    }
+}
+
+impl SgData {
+    /// Borrowing, zero-copy iterator over every segment of `self`, regardless
+    /// of variant. `Element::Zle` segments are expanded into slices of a
+    /// shared zero buffer rather than materializing owned zero vectors.
+    pub fn iter(&self) -> Iter<'_> {
+        match *self {
+            SgData::SgList(ref sglist) => Iter::SgList(SgListIter {
+                iovec: sglist.iovec,
+                idx: 0,
+                count: sglist.count as isize,
+                _marker: PhantomData,
+            }),
+            SgData::SgVec(ref sgvec) => Iter::SgVec(sgvec.iter()),
+            SgData::Direct(ref buf) => Iter::Direct(Some(buf)),
+            SgData::Element(ref elements) => Iter::Element(ElementIter {
+                elements: elements.iter(),
+                zero_remaining: 0,
+            }),
+        }
+    }
+
+    /// Writes every segment to `w` using real vectored I/O, expanding
+    /// `Element::Zle` runs from the shared zero buffer used by
+    /// [`SgData::iter`]. Drives the stable `Write::write_vectored` in a
+    /// manual retry loop, tracking how far a partial write advanced into
+    /// the segments, rather than the convenience `write_all_vectored`,
+    /// which is still unstable. Returns the total number of bytes written.
+    pub fn write_vectored<W>(&self, w: &mut W) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        let segments: Vec<&[u8]> = self.iter().collect();
+        let total = segments.iter().map(|segment| segment.len()).sum();
+
+        let mut seg_idx = 0;
+        let mut seg_offset = 0;
+        while seg_idx < segments.len() {
+            let slices: Vec<IoSlice> = segments[seg_idx..]
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| {
+                    if i == 0 {
+                        IoSlice::new(&segment[seg_offset..])
+                    } else {
+                        IoSlice::new(segment)
+                    }
+                })
+                .collect();
+
+            let mut written = w.write_vectored(&slices)?;
+            if written == 0 {
+                if slices.iter().all(|slice| slice.is_empty()) {
+                    break;
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ));
+            }
+
+            while written > 0 {
+                let remaining = segments[seg_idx].len() - seg_offset;
+                if written < remaining {
+                    seg_offset += written;
+                    written = 0;
+                } else {
+                    written -= remaining;
+                    seg_idx += 1;
+                    seg_offset = 0;
+                }
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Fills this `SgData`'s segments from `r` with a single vectored read,
+    /// skipping `Element::Zle` runs since they have no backing storage to
+    /// fill. Returns the number of bytes `r` actually supplied, which may be
+    /// less than the total segment length.
+    pub fn read_vectored<R>(&mut self, r: &mut R) -> io::Result<usize>
+    where
+        R: Read,
+    {
+        let mut slices = self.as_io_slices_mut();
+        r.read_vectored(&mut slices)
+    }
+
+    fn as_io_slices_mut(&mut self) -> Vec<IoSliceMut<'_>> {
+        match *self {
+            SgData::SgList(ref sglist) => (0..sglist.count as isize)
+                .map(|idx| unsafe {
+                    let iov = sglist.iovec.offset(idx);
+                    let base = (*iov).iov_base as *mut u8;
+                    let len = (*iov).iov_len as usize;
+                    IoSliceMut::new(slice::from_raw_parts_mut(base, len))
+                })
+                .collect(),
+            SgData::SgVec(ref mut sgvec) => sgvec
+                .iter_mut()
+                .map(|segment| IoSliceMut::new(segment.as_mut_slice()))
+                .collect(),
+            SgData::Direct(ref mut buf) => vec![IoSliceMut::new(buf.as_mut_slice())],
+            SgData::Element(ref mut elements) => elements
+                .iter_mut()
+                .filter_map(|elem| match *elem {
+                    Element::Zle(_) => None,
+                    Element::Iovec(ref iov) => Some(unsafe {
+                        let base = iov.iov_base as *mut u8;
+                        let len = iov.iov_len as usize;
+                        IoSliceMut::new(slice::from_raw_parts_mut(base, len))
+                    }),
+                    Element::Owned(ref mut buf) => Some(IoSliceMut::new(buf.as_mut_slice())),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Deserializes a single `Direct` buffer from a byte string, accepting both
+/// borrowed and owned byte buffers depending on what the format provides.
+fn deserialize_buffer<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_byte_buf(BufVisitor)
+}
+
+/// Deserializes the segments of an `SgVec` from a sequence of byte strings.
+fn deserialize_segments<'de, D>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_seq(SegmentsVisitor)
+}
+
+struct BufVisitor;
+
+impl<'de> de::Visitor<'de> for BufVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte buffer")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.to_vec())
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+}
+
+struct BufSeed;
+
+impl<'de> de::DeserializeSeed<'de> for BufSeed {
+    type Value = Vec<u8>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(BufVisitor)
+    }
+}
+
+struct SegmentsVisitor;
+
+impl<'de> de::Visitor<'de> for SegmentsVisitor {
+    type Value = Vec<Vec<u8>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of byte buffers")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        // Not preallocated from `seq.size_hint()`: some deserializers (e.g.
+        // bincode) report the raw wire length verbatim, which would let a
+        // corrupted/hostile length prefix force a huge upfront allocation.
+        let mut segments = Vec::new();
+        while let Some(segment) = seq.next_element_seed(BufSeed)? {
+            segments.push(segment);
+        }
+        Ok(segments)
+    }
+}
+
 /// Wrapper for a C-style scatter gather list
 #[derive(Debug, PartialEq)]
 pub struct SgList {
@@ -162,7 +507,7 @@ impl Serialize for SgList {
                 let len = (*iov).iov_len as usize;
                 slice::from_raw_parts(base, len)
             };
-            seq.serialize_element(buf)?;
+            seq.serialize_element(&BytesRef(buf))?;
         }
         seq.end()
     }
@@ -183,6 +528,11 @@ pub enum Element {
     Zle(usize),
     /// Regular `iovec`
     Iovec(iovec),
+    /// A literal buffer this `Element` owns outright, freed normally on
+    /// drop. Used for data decoded from the wire (see [`codec`] and
+    /// [`SparseElements`]), which has no backing C allocation to borrow and
+    /// must not be punned through the non-owning [`Element::Iovec`].
+    Owned(Vec<u8>),
 }
 
 impl Element {
@@ -210,6 +560,7 @@ impl fmt::Debug for Element {
         match *self {
             Zle(ref size) => write!(f, "Element::Zle({:?})", size),
             Iovec(ref iov) => write!(f, "Element::Iovec({:?}, {:?})", iov.iov_base, iov.iov_len),
+            Owned(ref buf) => write!(f, "Element::Owned({} bytes)", buf.len()),
         }
     }
 }
@@ -223,6 +574,7 @@ impl PartialEq for Element {
             (&Iovec(ref iov1), &Iovec(ref iov2)) => {
                 iov1.iov_base == iov2.iov_base && iov1.iov_len == iov2.iov_len
             }
+            (&Owned(ref buf1), &Owned(ref buf2)) => buf1 == buf2,
             _ => false,
         }
     }
@@ -237,15 +589,26 @@ impl Serialize for Element {
         S: Serializer,
     {
         match *self {
-            Element::Zle(ref size) => serializer.collect_seq(iter::repeat(0_u8).take(*size)),
+            Element::Zle(ref size) => {
+                // Emitted as one byte string like every other segment, so the
+                // `SgVec` form stays readable by `deserialize_segments`. Runs
+                // that fit in `ZERO_CHUNK` borrow it; longer runs are
+                // materialized as a zeroed buffer of `*size` bytes.
+                if *size <= ZERO_CHUNK.len() {
+                    serializer.serialize_bytes(&ZERO_CHUNK[..*size])
+                } else {
+                    serializer.serialize_bytes(&vec![0_u8; *size])
+                }
+            }
             Element::Iovec(ref iov) => {
                 let buf = unsafe {
                     let base = (*iov).iov_base as *const u8;
                     let len = (*iov).iov_len as usize;
                     slice::from_raw_parts(base, len)
                 };
-                serializer.collect_seq(buf)
+                serializer.serialize_bytes(buf)
             }
+            Element::Owned(ref buf) => serializer.serialize_bytes(buf),
         }
     }
 }
@@ -259,6 +622,325 @@ impl<'de> de::Deserialize<'de> for Element {
     }
 }
 
+/// Opt-in wrapper that serializes an [`SgData`] in a ZLE-aware sparse form:
+/// each segment is scanned for zero runs of at least `threshold` bytes, which
+/// are emitted as a bare length marker instead of literal zero bytes. Shorter
+/// zero runs stay folded into the surrounding literal span, so isolated zeros
+/// don't pay the marker's overhead. Construct via [`SgData::sparse`] or
+/// [`SgData::sparse_with_threshold`].
+///
+/// On the wire each segment becomes a sequence of `(kind, len[, bytes])`
+/// records: `kind = 0` is a zero run carrying only its length, `kind = 1` is
+/// a literal run followed by its `len` bytes. Runs never cross segment
+/// boundaries, so the scatter-gather structure survives the round trip.
+#[derive(Debug)]
+pub struct Sparse<'a> {
+    data: &'a SgData,
+    threshold: usize,
+}
+
+impl<'a> Sparse<'a> {
+    fn new(data: &'a SgData, threshold: usize) -> Self {
+        Sparse { data, threshold }
+    }
+}
+
+impl SgData {
+    /// Wraps `self` for sparse (ZLE-aware) serialization using the default
+    /// zero-run threshold.
+    pub fn sparse(&self) -> Sparse<'_> {
+        Sparse::new(self, DEFAULT_ZERO_RUN_THRESHOLD)
+    }
+
+    /// Wraps `self` for sparse serialization, treating only zero runs of at
+    /// least `threshold` bytes as ZLE markers.
+    pub fn sparse_with_threshold(&self, threshold: usize) -> Sparse<'_> {
+        Sparse::new(self, threshold)
+    }
+}
+
+/// A single literal or zero run within a sparse-encoded segment.
+enum Run<'a> {
+    Zero(usize),
+    Literal(&'a [u8]),
+}
+
+impl<'a> Serialize for Run<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Run::Zero(len) => {
+                let mut run = serializer.serialize_tuple(2)?;
+                run.serialize_element(&0_u8)?;
+                run.serialize_element(&(len as u64))?;
+                run.end()
+            }
+            Run::Literal(bytes) => {
+                let mut run = serializer.serialize_tuple(3)?;
+                run.serialize_element(&1_u8)?;
+                run.serialize_element(&(bytes.len() as u64))?;
+                run.serialize_element(&BytesRef(bytes))?;
+                run.end()
+            }
+        }
+    }
+}
+
+/// Splits `bytes` into literal/zero runs, treating only zero runs of at
+/// least `threshold` bytes as ZLE markers.
+fn scan_runs<'a>(bytes: &'a [u8], threshold: usize) -> Vec<Run<'a>> {
+    let mut runs = Vec::new();
+    let mut literal_start = 0;
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] != 0 {
+            idx += 1;
+            continue;
+        }
+
+        let zero_start = idx;
+        while idx < bytes.len() && bytes[idx] == 0 {
+            idx += 1;
+        }
+        let zero_len = idx - zero_start;
+
+        if zero_len >= threshold {
+            if zero_start > literal_start {
+                runs.push(Run::Literal(&bytes[literal_start..zero_start]));
+            }
+            runs.push(Run::Zero(zero_len));
+            literal_start = idx;
+        }
+    }
+
+    if literal_start < bytes.len() || runs.is_empty() {
+        runs.push(Run::Literal(&bytes[literal_start..]));
+    }
+
+    runs
+}
+
+/// One segment's runs, serialized as a nested sequence.
+struct SparseSegment<'a>(Vec<Run<'a>>);
+
+impl<'a> SparseSegment<'a> {
+    fn from_bytes(bytes: &'a [u8], threshold: usize) -> Self {
+        SparseSegment(scan_runs(bytes, threshold))
+    }
+
+    fn from_element(elem: &'a Element, threshold: usize) -> Self {
+        match *elem {
+            Element::Zle(size) => SparseSegment(vec![Run::Zero(size)]),
+            Element::Iovec(ref iov) => {
+                let buf = unsafe {
+                    let base = iov.iov_base as *const u8;
+                    let len = iov.iov_len as usize;
+                    slice::from_raw_parts(base, len)
+                };
+                SparseSegment::from_bytes(buf, threshold)
+            }
+            Element::Owned(ref buf) => SparseSegment::from_bytes(buf, threshold),
+        }
+    }
+}
+
+impl<'a> Serialize for SparseSegment<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(&self.0)
+    }
+}
+
+impl<'a> Serialize for Sparse<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self.data {
+            SgData::SgList(ref sglist) => {
+                let count = sglist.count as usize;
+                let mut seq = serializer.serialize_seq(Some(count))?;
+                for idx in 0..sglist.count as isize {
+                    let buf = unsafe {
+                        let iov = sglist.iovec.offset(idx);
+                        let base = (*iov).iov_base as *const u8;
+                        let len = (*iov).iov_len as usize;
+                        slice::from_raw_parts(base, len)
+                    };
+                    seq.serialize_element(&SparseSegment::from_bytes(buf, self.threshold))?;
+                }
+                seq.end()
+            }
+            SgData::SgVec(ref sgvec) => {
+                let mut seq = serializer.serialize_seq(Some(sgvec.len()))?;
+                for segment in sgvec {
+                    seq.serialize_element(&SparseSegment::from_bytes(segment, self.threshold))?;
+                }
+                seq.end()
+            }
+            SgData::Direct(ref buf) => {
+                let mut seq = serializer.serialize_seq(Some(1))?;
+                seq.serialize_element(&SparseSegment::from_bytes(buf, self.threshold))?;
+                seq.end()
+            }
+            SgData::Element(ref elements) => {
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                for elem in elements {
+                    seq.serialize_element(&SparseSegment::from_element(elem, self.threshold))?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+/// A decoded `(kind, len[, bytes])` run record, before it is turned into an [`Element`].
+enum RunRecord {
+    Zero(usize),
+    Literal(Vec<u8>),
+}
+
+impl RunRecord {
+    fn into_element(self) -> Element {
+        match self {
+            RunRecord::Zero(len) => Element::Zle(len),
+            RunRecord::Literal(buf) => Element::Owned(buf),
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for RunRecord {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(3, RunRecordVisitor)
+    }
+}
+
+struct RunRecordVisitor;
+
+impl<'de> de::Visitor<'de> for RunRecordVisitor {
+    type Value = RunRecord;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a (kind, len[, bytes]) run record")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let kind: u8 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let len: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+        match kind {
+            0 => Ok(RunRecord::Zero(len as usize)),
+            1 => {
+                let buf: Vec<u8> = seq
+                    .next_element_seed(BufSeed)?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                Ok(RunRecord::Literal(buf))
+            }
+            other => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(u64::from(other)),
+                &"0 (zero run) or 1 (literal run)",
+            )),
+        }
+    }
+}
+
+struct SparseRunsVisitor;
+
+impl<'de> de::Visitor<'de> for SparseRunsVisitor {
+    type Value = Vec<Element>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of (kind, len[, bytes]) runs")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        // Not preallocated from `seq.size_hint()`: some deserializers (e.g.
+        // bincode) report the raw wire length verbatim, which would let a
+        // corrupted/hostile length prefix force a huge upfront allocation.
+        let mut elements = Vec::new();
+        while let Some(run) = seq.next_element::<RunRecord>()? {
+            elements.push(run.into_element());
+        }
+        Ok(elements)
+    }
+}
+
+struct SparseRunsSeed;
+
+impl<'de> de::DeserializeSeed<'de> for SparseRunsSeed {
+    type Value = Vec<Element>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(SparseRunsVisitor)
+    }
+}
+
+struct SparseSegmentsVisitor;
+
+impl<'de> de::Visitor<'de> for SparseSegmentsVisitor {
+    type Value = Vec<Element>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of sparse-encoded segments")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut elements = Vec::new();
+        while let Some(segment) = seq.next_element_seed(SparseRunsSeed)? {
+            elements.extend(segment);
+        }
+        Ok(elements)
+    }
+}
+
+/// Deserializes sparse-encoded wire data (see [`Sparse`]) back into a flat
+/// `Vec<Element>`, expanding each segment's runs in place without merging
+/// zero runs across segment boundaries.
+pub fn deserialize_sparse<'de, D>(deserializer: D) -> Result<Vec<Element>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_seq(SparseSegmentsVisitor)
+}
+
+/// Owned result of [`deserialize_sparse`], useful for round-tripping through
+/// formats (like `bincode`) that deserialize a single top-level type.
+#[derive(Debug, PartialEq)]
+pub struct SparseElements(pub Vec<Element>);
+
+impl<'de> de::Deserialize<'de> for SparseElements {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserialize_sparse(deserializer).map(SparseElements)
+    }
+}
+
 fn _assert_impls() {
     fn assert_send<T: Send>() {}
     fn assert_sync<T: Sync>() {}
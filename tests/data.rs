@@ -8,7 +8,7 @@ use std::mem;
 
 use bincode::{deserialize, serialize};
 use libc::{c_int, c_void, iovec};
-use rexsgdata::{Element, SgData, SgList};
+use rexsgdata::{codec, Element, SgData, SgList, SparseElements};
 use serde_test::{assert_ser_tokens, Token};
 
 // NB - never use this code outside of the tests - it leaks memory
@@ -67,13 +67,7 @@ fn direct() {
                 variant: "Direct",
                 len: 1,
             },
-            Token::Seq { len: Some(5) },
-            Token::U8(12),
-            Token::U8(56),
-            Token::U8(34),
-            Token::U8(255),
-            Token::U8(0),
-            Token::SeqEnd,
+            Token::Bytes(&[12, 56, 34, 255, 0]),
             Token::TupleVariantEnd,
         ],
     );
@@ -92,15 +86,8 @@ fn sgvec() {
                 len: 1,
             },
             Token::Seq { len: Some(2) },
-            Token::Seq { len: Some(3) },
-            Token::U8(12),
-            Token::U8(56),
-            Token::U8(76),
-            Token::SeqEnd,
-            Token::Seq { len: Some(2) },
-            Token::U8(128),
-            Token::U8(255),
-            Token::SeqEnd,
+            Token::Bytes(&[12, 56, 76]),
+            Token::Bytes(&[128, 255]),
             Token::SeqEnd,
             Token::TupleVariantEnd,
         ],
@@ -120,15 +107,8 @@ fn sglist() {
                 len: 1,
             },
             Token::Seq { len: Some(2) },
-            Token::Seq { len: Some(3) },
-            Token::U8(12),
-            Token::U8(56),
-            Token::U8(76),
-            Token::SeqEnd,
-            Token::Seq { len: Some(2) },
-            Token::U8(128),
-            Token::U8(255),
-            Token::SeqEnd,
+            Token::Bytes(&[12, 56, 76]),
+            Token::Bytes(&[128, 255]),
             Token::SeqEnd,
             Token::TupleVariantEnd,
         ],
@@ -149,19 +129,8 @@ fn element_zero() {
                 len: 1,
             },
             Token::Seq { len: Some(2) },
-            Token::Seq { len: Some(4) },
-            Token::U8(0),
-            Token::U8(0),
-            Token::U8(0),
-            Token::U8(0),
-            Token::SeqEnd,
-            Token::Seq { len: Some(5) },
-            Token::U8(0),
-            Token::U8(0),
-            Token::U8(0),
-            Token::U8(0),
-            Token::U8(0),
-            Token::SeqEnd,
+            Token::Bytes(&[0, 0, 0, 0]),
+            Token::Bytes(&[0, 0, 0, 0, 0]),
             Token::SeqEnd,
             Token::TupleVariantEnd,
         ],
@@ -185,17 +154,8 @@ fn element_iovec() {
                 len: 1,
             },
             Token::Seq { len: Some(2) },
-            Token::Seq { len: Some(3) },
-            Token::U8(36),
-            Token::U8(123),
-            Token::U8(234),
-            Token::SeqEnd,
-            Token::Seq { len: Some(4) },
-            Token::U8(87),
-            Token::U8(187),
-            Token::U8(211),
-            Token::U8(45),
-            Token::SeqEnd,
+            Token::Bytes(&[36, 123, 234]),
+            Token::Bytes(&[87, 187, 211, 45]),
             Token::SeqEnd,
             Token::TupleVariantEnd,
         ],
@@ -220,20 +180,322 @@ fn element_mixed() {
                 len: 1,
             },
             Token::Seq { len: Some(2) },
-            Token::Seq { len: Some(3) },
-            Token::U8(36),
-            Token::U8(123),
-            Token::U8(234),
+            Token::Bytes(&[36, 123, 234]),
+            Token::Bytes(&[0, 0, 0, 0, 0]),
             Token::SeqEnd,
-            Token::Seq { len: Some(5) },
-            Token::U8(0),
-            Token::U8(0),
-            Token::U8(0),
-            Token::U8(0),
+            Token::TupleVariantEnd,
+        ],
+    );
+}
+
+#[test]
+fn sparse_zero_run() {
+    let mut buf = vec![1, 2, 3];
+    buf.extend(vec![0_u8; 20]);
+    buf.extend(vec![9, 8]);
+    let data: SgData = buf.into();
+
+    assert_ser_tokens(
+        &data.sparse(),
+        &[
+            Token::Seq { len: Some(1) },
+            Token::Seq { len: Some(3) },
+            Token::Tuple { len: 3 },
+            Token::U8(1),
+            Token::U64(3),
+            Token::Bytes(&[1, 2, 3]),
+            Token::TupleEnd,
+            Token::Tuple { len: 2 },
             Token::U8(0),
+            Token::U64(20),
+            Token::TupleEnd,
+            Token::Tuple { len: 3 },
+            Token::U8(1),
+            Token::U64(2),
+            Token::Bytes(&[9, 8]),
+            Token::TupleEnd,
             Token::SeqEnd,
             Token::SeqEnd,
-            Token::TupleVariantEnd,
         ],
     );
 }
+
+#[test]
+fn sparse_short_zero_run_stays_literal() {
+    let buf = vec![1_u8, 2, 3, 0, 0, 0, 0];
+    let data: SgData = buf.clone().into();
+
+    assert_ser_tokens(
+        &data.sparse(),
+        &[
+            Token::Seq { len: Some(1) },
+            Token::Seq { len: Some(1) },
+            Token::Tuple { len: 3 },
+            Token::U8(1),
+            Token::U64(buf.len() as u64),
+            Token::Bytes(&[1, 2, 3, 0, 0, 0, 0]),
+            Token::TupleEnd,
+            Token::SeqEnd,
+            Token::SeqEnd,
+        ],
+    );
+}
+
+#[test]
+fn sparse_round_trip() {
+    let mut buf = vec![7_u8; 5];
+    buf.extend(vec![0_u8; 32]);
+
+    let data: SgData = buf.clone().into();
+    let encoded = serialize(&data.sparse()).unwrap();
+    let SparseElements(elements) = deserialize(&encoded).unwrap();
+
+    assert_eq!(elements.len(), 2);
+    match elements[0] {
+        Element::Owned(ref bytes) => assert_eq!(bytes, &buf[..5]),
+        ref other => panic!("expected a literal element, got {:?}", other),
+    }
+    assert_eq!(elements[1], Element::zero(32));
+}
+
+#[test]
+fn codec_round_trip_zero_only() {
+    let data: SgData = vec![Element::zero(10), Element::zero(5)]
+        .into_iter()
+        .collect();
+
+    let mut encoded = Vec::new();
+    let written = codec::write_to(&data, &mut encoded).unwrap();
+    assert_eq!(written, encoded.len());
+
+    let decoded = codec::read_from(&mut &encoded[..]).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn codec_round_trip_mixed() {
+    let literal = vec![1_u8, 2, 3, 4];
+    let data: SgData = vec![vec_into_iovec(literal.clone())]
+        .into_iter()
+        .map(Element::from)
+        .chain(Some(Element::zero(4096)))
+        .collect();
+
+    let mut encoded = Vec::new();
+    let written = codec::write_to(&data, &mut encoded).unwrap();
+    assert_eq!(written, encoded.len());
+
+    match codec::read_from(&mut &encoded[..]).unwrap() {
+        SgData::Element(elements) => {
+            assert_eq!(elements.len(), 2);
+            match elements[0] {
+                Element::Owned(ref bytes) => assert_eq!(bytes, &literal),
+                ref other => panic!("expected a literal element, got {:?}", other),
+            }
+            assert_eq!(elements[1], Element::zero(4096));
+        }
+        ref other => panic!("expected SgData::Element, got {:?}", other),
+    }
+}
+
+#[test]
+fn codec_round_trip_sgvec() {
+    let data: SgData = vec![vec![10, 20, 30], vec![40, 50]].into();
+
+    let mut encoded = Vec::new();
+    codec::write_to(&data, &mut encoded).unwrap();
+
+    match codec::read_from(&mut &encoded[..]).unwrap() {
+        SgData::Element(elements) => {
+            let bufs: Vec<&[u8]> = elements
+                .iter()
+                .map(|elem| match *elem {
+                    Element::Owned(ref buf) => buf.as_slice(),
+                    ref other => panic!("expected a literal element, got {:?}", other),
+                })
+                .collect();
+            assert_eq!(bufs, vec![&[10_u8, 20, 30][..], &[40_u8, 50][..]]);
+        }
+        ref other => panic!("expected SgData::Element, got {:?}", other),
+    }
+}
+
+#[test]
+fn codec_rejects_oversized_segment_count() {
+    let mut frame = vec![1_u8]; // version
+    frame.extend(&0xFFFF_FFFF_u32.to_be_bytes()); // segment count
+
+    let err = codec::read_from(&mut &frame[..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn codec_rejects_literal_longer_than_available_input() {
+    let mut frame = vec![1_u8]; // version
+    frame.extend(&1_u32.to_be_bytes()); // segment count
+    frame.push(0); // kind = literal
+    frame.extend(&u64::MAX.to_be_bytes()); // len
+
+    let err = codec::read_from(&mut &frame[..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn iter_direct() {
+    let data: SgData = vec![1, 2, 3].into();
+    let segments: Vec<&[u8]> = data.iter().collect();
+    assert_eq!(segments, vec![&[1_u8, 2, 3][..]]);
+}
+
+#[test]
+fn iter_sgvec() {
+    let data: SgData = vec![vec![1, 2], vec![3, 4, 5]].into();
+    let segments: Vec<&[u8]> = data.iter().collect();
+    assert_eq!(segments, vec![&[1_u8, 2][..], &[3_u8, 4, 5][..]]);
+}
+
+#[test]
+fn iter_sglist() {
+    let data: SgData = create_sglist(vec![vec![1, 2], vec![3, 4, 5]]).into();
+    let segments: Vec<&[u8]> = data.iter().collect();
+    assert_eq!(segments, vec![&[1_u8, 2][..], &[3_u8, 4, 5][..]]);
+}
+
+#[test]
+fn iter_element_expands_zle() {
+    let data: SgData = vec![vec_into_iovec(vec![9, 8])]
+        .into_iter()
+        .map(Element::from)
+        .chain(Some(Element::zero(5000)))
+        .collect();
+
+    let segments: Vec<&[u8]> = data.iter().collect();
+    assert_eq!(segments[0], &[9_u8, 8][..]);
+
+    let zero_bytes: usize = segments[1..].iter().map(|seg| seg.len()).sum();
+    assert_eq!(zero_bytes, 5000);
+    assert!(segments[1..].iter().all(|seg| seg.iter().all(|&b| b == 0)));
+}
+
+#[test]
+fn iter_element_skips_long_run_of_empty_zle_without_overflowing_stack() {
+    let data: SgData = (0..1_000_000).map(|_| Element::zero(0)).collect();
+    assert_eq!(data.iter().count(), 0);
+}
+
+#[test]
+fn into_iter_sglist() {
+    let data: SgData = create_sglist(vec![vec![1, 2], vec![3, 4, 5]]).into();
+    let segments: Vec<Vec<u8>> = data.into_iter().collect();
+    assert_eq!(segments, vec![vec![1, 2], vec![3, 4, 5]]);
+}
+
+#[test]
+fn into_iter_element_expands_zle() {
+    let data: SgData = vec![vec_into_iovec(vec![9, 8])]
+        .into_iter()
+        .map(Element::from)
+        .chain(Some(Element::zero(3)))
+        .collect();
+
+    let segments: Vec<Vec<u8>> = data.into_iter().collect();
+    assert_eq!(segments, vec![vec![9, 8], vec![0, 0, 0]]);
+}
+
+#[test]
+fn write_vectored_concatenates_segments() {
+    let data: SgData = vec![vec![1, 2, 3], vec![4, 5]].into();
+
+    let mut out = Vec::new();
+    let written = data.write_vectored(&mut out).unwrap();
+
+    assert_eq!(written, 5);
+    assert_eq!(out, vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn write_vectored_expands_zle() {
+    let data: SgData = vec![vec_into_iovec(vec![9, 8])]
+        .into_iter()
+        .map(Element::from)
+        .chain(Some(Element::zero(4)))
+        .collect();
+
+    let mut out = Vec::new();
+    let written = data.write_vectored(&mut out).unwrap();
+
+    assert_eq!(written, 6);
+    assert_eq!(out, vec![9, 8, 0, 0, 0, 0]);
+}
+
+/// A `Write` that only ever accepts a handful of bytes per call, forcing
+/// `write_vectored`'s retry loop to advance partway through a segment (and
+/// across segment boundaries) instead of draining everything in one shot.
+struct PartialWriter {
+    out: Vec<u8>,
+    max_per_call: usize,
+}
+
+impl std::io::Write for PartialWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let take = std::cmp::min(buf.len(), self.max_per_call);
+        self.out.extend_from_slice(&buf[..take]);
+        Ok(take)
+    }
+
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice]) -> std::io::Result<usize> {
+        let mut remaining = self.max_per_call;
+        let mut written = 0;
+        for buf in bufs {
+            if remaining == 0 {
+                break;
+            }
+            let take = std::cmp::min(buf.len(), remaining);
+            self.out.extend_from_slice(&buf[..take]);
+            written += take;
+            remaining -= take;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_vectored_handles_partial_writes() {
+    let data: SgData = vec![vec![1, 2, 3], vec![4, 5], vec![6, 7, 8, 9]].into();
+
+    let mut w = PartialWriter {
+        out: Vec::new(),
+        max_per_call: 2,
+    };
+    let written = data.write_vectored(&mut w).unwrap();
+
+    assert_eq!(written, 9);
+    assert_eq!(w.out, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+}
+
+#[test]
+fn read_vectored_fills_sgvec_segments() {
+    let mut data: SgData = vec![vec![0; 3], vec![0; 2]].into();
+    let mut source: &[u8] = &[1, 2, 3, 4, 5];
+
+    let read = data.read_vectored(&mut source).unwrap();
+
+    assert_eq!(read, 5);
+    assert_eq!(data, vec![vec![1, 2, 3], vec![4, 5]].into());
+}
+
+#[test]
+fn read_vectored_fills_sglist_buffers() {
+    let mut data: SgData = create_sglist(vec![vec![0; 3], vec![0; 2]]).into();
+    let mut source: &[u8] = &[9, 8, 7, 6, 5];
+
+    let read = data.read_vectored(&mut source).unwrap();
+
+    assert_eq!(read, 5);
+    let segments: Vec<&[u8]> = data.iter().collect();
+    assert_eq!(segments, vec![&[9_u8, 8, 7][..], &[6_u8, 5][..]]);
+}